@@ -0,0 +1,122 @@
+"""
+Runs the notify script end to end in a throwaway home directory.
+
+Run with: python3 -m unittest discover tests
+
+"""
+import importlib.util
+import json
+import os
+import socket
+import subprocess
+import sys
+import tempfile
+import time
+import unittest
+import urllib.error
+import urllib.request
+
+NOTIFY = os.path.join(os.path.dirname(os.path.dirname(
+    os.path.abspath(__file__))), 'notify')
+# the settings needed to run without prompting; nothing listens on
+# port 1, so email delivery always fails
+BASE_CONFIG = (
+    ('server', '127.0.0.1'), ('port', '1'),
+    ('from_address', 'notify@localhost'), ('password', 'unused'))
+
+
+@unittest.skipUnless(importlib.util.find_spec('biogl'), 'requires biogl')
+class NotifyTestCase(unittest.TestCase):
+    def setUp(self):
+        self.home = tempfile.TemporaryDirectory()
+        self.addCleanup(self.home.cleanup)
+
+    def write_config(self, *lines):
+        path = os.path.join(self.home.name, '.notify.config')
+        with open(path, 'w') as f:
+            for line in BASE_CONFIG + lines:
+                f.write('\t'.join(line) + '\n')
+        os.chmod(path, 0o600)
+
+    def run_notify(self, *args):
+        env = dict(os.environ, HOME=self.home.name)
+        return subprocess.run(
+            [sys.executable, NOTIFY] + list(args),
+            stdin=subprocess.DEVNULL,
+            stdout=subprocess.PIPE,
+            stderr=subprocess.PIPE,
+            universal_newlines=True,
+            cwd=self.home.name,
+            env=env,
+            timeout=120)
+
+
+class FallbackTest(NotifyTestCase):
+    def test_rule_suppressed_run_skips_fallback(self):
+        json_log = os.path.join(self.home.name, 'runs.jsonl')
+        self.write_config(
+            ('json_log', json_log),
+            ('fallback', 'email file'),
+            ('rule', 'exit=success', 'channel=none'))
+        result = self.run_notify('-e', 'me@example.com', 'true')
+        self.assertEqual(result.returncode, 0, result.stderr)
+        self.assertNotIn('fallback channel', result.stderr)
+        self.assertFalse(os.path.exists(json_log))
+
+    def test_failed_primary_uses_fallback(self):
+        json_log = os.path.join(self.home.name, 'runs.jsonl')
+        self.write_config(
+            ('json_log', json_log),
+            ('fallback', 'email file'))
+        result = self.run_notify('-e', 'me@example.com', 'true')
+        self.assertIn('sent via fallback channel file', result.stderr)
+        with open(json_log) as f:
+            record = json.loads(f.readline())
+        self.assertEqual(record['undelivered'], ['email'])
+
+
+class RelayServerTest(NotifyTestCase):
+    def setUp(self):
+        super().setUp()
+        self.write_config()
+        with socket.socket() as s:
+            s.bind(('127.0.0.1', 0))
+            self.port = s.getsockname()[1]
+        server = subprocess.Popen(
+            [sys.executable, NOTIFY, '--relay_server', str(self.port)],
+            stdin=subprocess.DEVNULL,
+            stdout=subprocess.DEVNULL,
+            stderr=subprocess.DEVNULL,
+            env=dict(os.environ, HOME=self.home.name))
+        self.addCleanup(server.wait)
+        self.addCleanup(server.terminate)
+        deadline = time.time() + 30
+        while True:
+            try:
+                socket.create_connection(('127.0.0.1', self.port)).close()
+                break
+            except OSError:
+                if time.time() > deadline:
+                    raise
+                time.sleep(0.1)
+
+    def post(self, body):
+        request = urllib.request.Request(
+            'http://127.0.0.1:{}/reports'.format(self.port),
+            data=body.encode('utf-8'),
+            headers={'Content-Type': 'application/json'})
+        try:
+            with urllib.request.urlopen(request, timeout=30) as response:
+                return response.status
+        except urllib.error.HTTPError as e:
+            return e.code
+
+    def test_rejects_non_object_bodies(self):
+        for body in ('[1, 2]', '"report"', '{"report": []}',
+                     '{"report": {}, "recipients": "me@example.com"}'):
+            with self.subTest(body=body):
+                self.assertEqual(self.post(body), 400)
+
+
+if __name__ == '__main__':
+    unittest.main()